@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::{ReadingList, ReadingListItem};
+
+/// Thin wrapper around the local `state.db` that remembers which reading-list
+/// items have already been synced so runs can stage incremental changes
+/// instead of rewriting everything.
+pub struct Db {
+    conn: Connection,
+}
+
+/// A stored row plus the hash of the Markdown we last wrote for it.
+struct StoredItem {
+    title: String,
+    content_hash: String,
+}
+
+/// An item that is no longer present upstream. Keyed by `url` (the DB primary
+/// key) but carries its last-known `title` so the right Markdown file can be
+/// deleted.
+pub struct RemovedItem {
+    pub url: String,
+    pub title: String,
+}
+
+/// The difference between the freshly fetched list and what is on disk.
+pub struct Diff {
+    /// Items whose Markdown needs to be (re)written, paired with their hash.
+    pub changed: Vec<(ReadingListItem, String)>,
+    /// Items that disappeared upstream; their files and DB rows must be removed.
+    pub removed: Vec<RemovedItem>,
+    /// Old titles of renamed items whose stale Markdown files must be deleted
+    /// (the DB row is re-keyed by `url`, so only the file is orphaned).
+    pub stale_files: Vec<String>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty() && self.stale_files.is_empty()
+    }
+}
+
+impl Db {
+    /// Open (creating if needed) the state DB at `path` and ensure the schema.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Db, rusqlite::Error> {
+        let conn = Connection::open(path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS synced_items (
+                url          TEXT PRIMARY KEY,
+                title        TEXT NOT NULL,
+                date         TEXT NOT NULL,
+                content_hash TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(Db { conn })
+    }
+
+    /// Compare `list` against the stored rows, returning the items that need to
+    /// be written and the titles of items that were removed upstream.
+    pub fn diff(&self, list: &ReadingList) -> Result<Diff, rusqlite::Error> {
+        let mut stored = self.load()?;
+
+        let mut changed = Vec::new();
+        let mut stale_files = Vec::new();
+        for item in list.0.iter() {
+            let hash = content_hash(item);
+            match stored.remove(&item.url) {
+                Some(prev) if prev.content_hash == hash => {}
+                Some(prev) => {
+                    // Same URL, different content: a renamed item leaves its old
+                    // Markdown file behind, so schedule it for deletion.
+                    if prev.title != item.title {
+                        stale_files.push(prev.title);
+                    }
+                    changed.push((clone_item(item), hash));
+                }
+                None => changed.push((clone_item(item), hash)),
+            }
+        }
+
+        // Anything left in `stored` is no longer present upstream.
+        let removed = stored
+            .into_iter()
+            .map(|(url, s)| RemovedItem { url, title: s.title })
+            .collect();
+
+        Ok(Diff {
+            changed,
+            removed,
+            stale_files,
+        })
+    }
+
+    /// Persist the newly written items and forget the removed ones.
+    pub fn record(&self, diff: &Diff) -> Result<(), rusqlite::Error> {
+        for (item, hash) in diff.changed.iter() {
+            self.conn.execute(
+                "INSERT INTO synced_items (url, title, date, content_hash)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(url) DO UPDATE SET
+                    title = excluded.title,
+                    date = excluded.date,
+                    content_hash = excluded.content_hash",
+                params![item.url, item.title, item.date, hash],
+            )?;
+        }
+
+        for item in diff.removed.iter() {
+            self.conn
+                .execute("DELETE FROM synced_items WHERE url = ?1", params![item.url])?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&self) -> Result<HashMap<String, StoredItem>, rusqlite::Error> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT url, title, content_hash FROM synced_items")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                StoredItem {
+                    title: row.get(1)?,
+                    content_hash: row.get(2)?,
+                },
+            ))
+        })?;
+
+        let mut map = HashMap::new();
+        for row in rows {
+            let (url, item) = row?;
+            map.insert(url, item);
+        }
+        Ok(map)
+    }
+}
+
+fn clone_item(item: &ReadingListItem) -> ReadingListItem {
+    ReadingListItem {
+        id: item.id.clone(),
+        url: item.url.clone(),
+        title: item.title.clone(),
+        date: item.date.clone(),
+        last_edited: item.last_edited.clone(),
+    }
+}
+
+/// Stable FNV-1a hash of the fields that drive the generated Markdown, so an
+/// unchanged item produces the same hash across runs. `last_edited` (Notion's
+/// `last_edited_time`) is included so body edits — which leave `title`/`date`
+/// unchanged — still register as a change and get re-rendered.
+fn content_hash(item: &ReadingListItem) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for field in [&item.title, &item.date, &item.url, &item.last_edited] {
+        for byte in field.as_bytes() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        hash ^= 0x1f;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
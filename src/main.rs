@@ -2,23 +2,32 @@ use git2::{Repository, Signature, ObjectType, IndexAddOption, Direction};
 use rustop::opts;
 use std::{fs::File, io::Write};
 
+mod dbctx;
+mod notion;
+mod server;
+
 const TARGET_REPO_URL: &str = "https://github.com/Almo7aya/almo7aya.github.io.git";
 const CLONED_REPO_PATH: &str = "dist/";
-const NOTION_API_URL: &str = "https://api.notion.com/v1/databases/";
 const DIST_PATH: &str = "dist/content/reading/";
+// Kept outside the cloned `dist/` tree so the state DB is never committed.
+const STATE_DB_PATH: &str = "state.db";
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Args {
     gh_token: String,
     notion_token: String,
     notion_database_id: String,
+    webhook_secret: Option<String>,
+    serve: bool,
 }
 
 #[derive(Debug)]
 struct ReadingListItem {
+    id: String,
     url: String,
     title: String,
     date: String,
+    last_edited: String,
 }
 
 #[derive(Debug)]
@@ -27,23 +36,55 @@ struct ReadingList(Vec<ReadingListItem>);
 #[tokio::main]
 async fn main() {
     let args = parse_args();
-    let database_content = get_database_from_notion(&args)
-        .await
-        .expect("Failed to load database");
+
+    if args.serve {
+        server::serve(args).await;
+        return;
+    }
+
+    run_sync(&args).await.expect("Failed to sync reading list");
+
+    println!("Done uploading files");
+}
+
+/// Run the full fetch→write→commit→push pipeline once. Shared by the one-shot
+/// CLI invocation and the `--serve` webhook handler.
+async fn run_sync(args: &Args) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let client = notion::NotionClient::new(&args.notion_token);
+    let database_content = client.query_database(&args.notion_database_id).await?;
 
     let data = get_formatted_data_from_database(&database_content)
-        .expect("Failed to parse notion database");
+        .ok_or("Failed to parse notion database")?;
 
-    let repo = clone_target_repo_from_gh().expect("Failed to clone target repo");
+    let repo = clone_target_repo_from_gh().ok_or("Failed to clone target repo")?;
 
-    write_mdfiles_to_dist(&data).expect("Failed to write MD files");
+    let db = dbctx::Db::open(STATE_DB_PATH)?;
+    let diff = db.diff(&data)?;
 
-    setup_target_repo_commit_and_push(&repo, &args).expect("Failed to commit to repo");
+    apply_diff_to_dist(&client, &diff).await?;
 
-    println!("Done uploading files");
+    write_atomfeed_to_dist(&data)?;
+
+    setup_target_repo_commit_and_push(&repo, args, diff.is_empty())?;
+
+    // Only mark items as synced once the push has actually landed, so a failed
+    // push is retried on the next run instead of being silently dropped.
+    db.record(&diff)?;
+
+    Ok(())
 }
 
-fn setup_target_repo_commit_and_push(repo: &Repository, args: &Args) -> Result<(), git2::Error> {
+fn setup_target_repo_commit_and_push(
+    repo: &Repository,
+    args: &Args,
+    skip: bool,
+) -> Result<(), git2::Error> {
+    // Nothing changed since the last run, so there is nothing to commit or push.
+    if skip {
+        println!("No changes to sync");
+        return Ok(());
+    }
+
     let mut config = repo.config()?;
     config.set_str(
         format!("url.{}.insteadOf", args.gh_token).as_str(),
@@ -101,27 +142,103 @@ fn clone_target_repo_from_gh() -> Option<Repository> {
     Some(repo)
 }
 
-fn write_mdfiles_to_dist(list: &ReadingList) -> Result<(), std::io::Error> {
-    std::fs::create_dir_all(DIST_PATH).unwrap();
+async fn apply_diff_to_dist(
+    client: &notion::NotionClient,
+    diff: &dbctx::Diff,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    std::fs::create_dir_all(DIST_PATH)?;
 
-    for item in list.0.iter() {
+    for (item, _) in diff.changed.iter() {
+        let body = client.render_page_markdown(&item.id).await?;
         let mut file = File::create(format!("{}{}.{}", DIST_PATH, item.title, "md"))
             .expect(format!("Failed to open file {}{}.md", DIST_PATH, item.title).as_str());
-        let content = format!(
-            "\
+        file.write_all(build_md_content(item, &body).as_bytes())?;
+    }
+
+    let stale_titles = diff
+        .removed
+        .iter()
+        .map(|item| &item.title)
+        .chain(diff.stale_files.iter());
+    for title in stale_titles {
+        let path = format!("{}{}.{}", DIST_PATH, title, "md");
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn build_md_content(item: &ReadingListItem, body: &str) -> String {
+    format!(
+        "\
 ---
 title: \"{}\"
 date: {}
 draft: false
 affiliatelink: {}
 ---
+{}
+
 {}
 ",
-            item.title, item.date, item.url, item.url
-        );
-        file.write_all(content.as_bytes())?;
+        item.title, item.date, item.url, item.url, body
+    )
+}
+
+fn write_atomfeed_to_dist(
+    list: &ReadingList,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    std::fs::create_dir_all(DIST_PATH)?;
+
+    let mut entries = Vec::with_capacity(list.0.len());
+    let mut feed_updated: Option<chrono::DateTime<chrono::FixedOffset>> = None;
+
+    for item in list.0.iter() {
+        let updated = match chrono::DateTime::parse_from_rfc3339(&item.date) {
+            Ok(updated) => updated,
+            Err(e) => {
+                eprintln!("Skipping {} in Atom feed: unparseable date {}: {}", item.title, item.date, e);
+                continue;
+            }
+        };
+        if feed_updated.map_or(true, |latest| updated > latest) {
+            feed_updated = Some(updated);
+        }
+
+        let link = atom_syndication::LinkBuilder::default()
+            .href(item.url.clone())
+            .build();
+
+        let entry = atom_syndication::EntryBuilder::default()
+            .title(item.title.clone())
+            .id(item.url.clone())
+            .link(link)
+            .published(Some(updated))
+            .updated(updated)
+            .build();
+
+        entries.push(entry);
     }
 
+    // Fall back to a fixed epoch (never wall-clock) so a list with no parseable
+    // dates yields the same feed on every run.
+    let epoch = chrono::DateTime::parse_from_rfc3339("1970-01-01T00:00:00Z")
+        .expect("epoch is valid RFC 3339");
+
+    let feed = atom_syndication::FeedBuilder::default()
+        .title("Reading list")
+        .id(TARGET_REPO_URL)
+        .updated(feed_updated.unwrap_or(epoch))
+        .entries(entries)
+        .build();
+
+    let file = File::create(format!("{}{}", DIST_PATH, "index.xml"))?;
+    feed.write_to(file)?;
+
     Ok(())
 }
 
@@ -133,6 +250,7 @@ fn get_formatted_data_from_database(database: &serde_json::Value) -> Option<Read
         let props = record.get("properties")?;
 
         let item = ReadingListItem {
+            id: record.get("id")?.as_str()?.into(),
             url: props.get("URL")?.get("url")?.as_str()?.into(),
             title: props
                 .get("Name")?
@@ -144,6 +262,7 @@ fn get_formatted_data_from_database(database: &serde_json::Value) -> Option<Read
                 .replace("/", "-")
                 .into(),
             date: record.get("created_time")?.as_str()?.into(),
+            last_edited: record.get("last_edited_time")?.as_str()?.into(),
         };
 
         reading_list.0.push(item);
@@ -152,29 +271,13 @@ fn get_formatted_data_from_database(database: &serde_json::Value) -> Option<Read
     Some(reading_list)
 }
 
-async fn get_database_from_notion(args: &Args) -> Result<serde_json::Value, reqwest::Error> {
-    let url = format!("{}{}/query", NOTION_API_URL, args.notion_database_id);
-
-    let client = reqwest::Client::new();
-    let res: serde_json::Value = client
-        .post(url)
-        .header("Notion-Version", "2022-06-28")
-        .header("authorization", format!("Bearer {}", args.notion_token))
-        .header("accept", "application/json")
-        .header("content-type", "application/json")
-        .send()
-        .await?
-        .json()
-        .await?;
-
-    Ok(res)
-}
-
 fn parse_args() -> Args {
     let (args, _) = opts! {
         param gh_token:Option<String>, desc:"github token.";
         param notion_token:Option<String>, desc:"notion token.";
         param notion_database_id:Option<String>, desc:"notion database id.";
+        param webhook_secret:Option<String>, desc:"shared secret for the /sync webhook HMAC.";
+        flag serve, desc:"run as a daemon exposing a webhook-triggered /sync endpoint.";
     }
     .parse_or_exit();
 
@@ -184,5 +287,9 @@ fn parse_args() -> Args {
         notion_database_id: args
             .notion_database_id
             .unwrap_or("notion_database_id".to_owned()),
+        // Left as-is (no placeholder default): a missing secret must fail the
+        // `--serve` path loudly rather than exposing a publicly-known constant.
+        webhook_secret: args.webhook_secret,
+        serve: args.serve,
     }
 }
@@ -0,0 +1,201 @@
+use serde_json::Value;
+
+const NOTION_VERSION: &str = "2022-06-28";
+const DATABASES_URL: &str = "https://api.notion.com/v1/databases/";
+const BLOCKS_URL: &str = "https://api.notion.com/v1/blocks/";
+
+/// Small wrapper around the Notion REST API covering the database, page and
+/// block endpoints this tool needs.
+pub struct NotionClient {
+    client: reqwest::Client,
+    token: String,
+}
+
+impl NotionClient {
+    pub fn new(token: &str) -> NotionClient {
+        NotionClient {
+            client: reqwest::Client::new(),
+            token: token.to_owned(),
+        }
+    }
+
+    /// Query a database, following the `has_more`/`next_cursor` cursor protocol
+    /// so every page of records is returned in a single merged `results` array.
+    pub async fn query_database(&self, database_id: &str) -> Result<Value, reqwest::Error> {
+        let url = format!("{}{}/query", DATABASES_URL, database_id);
+        let mut results: Vec<Value> = Vec::new();
+        let mut start_cursor: Option<String> = None;
+
+        loop {
+            let body = match &start_cursor {
+                Some(cursor) => serde_json::json!({ "start_cursor": cursor }),
+                None => serde_json::json!({}),
+            };
+
+            let page: Value = self
+                .post(&url)
+                .json(&body)
+                .send()
+                .await?
+                .json()
+                .await?;
+
+            if let Some(records) = page.get("results").and_then(|r| r.as_array()) {
+                results.extend(records.iter().cloned());
+            }
+
+            match next_cursor(&page) {
+                Some(cursor) => start_cursor = Some(cursor),
+                None => break,
+            }
+        }
+
+        Ok(serde_json::json!({ "results": results }))
+    }
+
+    /// Fetch all child blocks of a page/block, paginated via the same cursor
+    /// protocol as [`query_database`].
+    pub async fn get_block_children(&self, block_id: &str) -> Result<Vec<Value>, reqwest::Error> {
+        let url = format!("{}{}/children", BLOCKS_URL, block_id);
+        let mut results: Vec<Value> = Vec::new();
+        let mut start_cursor: Option<String> = None;
+
+        loop {
+            let mut req = self.get(&url);
+            if let Some(cursor) = &start_cursor {
+                req = req.query(&[("start_cursor", cursor)]);
+            }
+
+            let page: Value = req.send().await?.json().await?;
+
+            if let Some(blocks) = page.get("results").and_then(|r| r.as_array()) {
+                results.extend(blocks.iter().cloned());
+            }
+
+            match next_cursor(&page) {
+                Some(cursor) => start_cursor = Some(cursor),
+                None => break,
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Fetch a page's blocks and render them into a Markdown body.
+    pub async fn render_page_markdown(&self, page_id: &str) -> Result<String, reqwest::Error> {
+        let blocks = self.get_block_children(page_id).await?;
+        Ok(render_blocks(&blocks))
+    }
+
+    fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client
+            .post(url)
+            .header("Notion-Version", NOTION_VERSION)
+            .header("authorization", format!("Bearer {}", self.token))
+            .header("accept", "application/json")
+            .header("content-type", "application/json")
+    }
+
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client
+            .get(url)
+            .header("Notion-Version", NOTION_VERSION)
+            .header("authorization", format!("Bearer {}", self.token))
+            .header("accept", "application/json")
+    }
+}
+
+/// Pull the next cursor out of a paginated response, or `None` once `has_more`
+/// is false or the cursor is `null`.
+fn next_cursor(page: &Value) -> Option<String> {
+    if page.get("has_more").and_then(|h| h.as_bool()) != Some(true) {
+        return None;
+    }
+    page.get("next_cursor")
+        .and_then(|c| c.as_str())
+        .map(|c| c.to_owned())
+}
+
+/// Convert the common Notion block types into their Markdown equivalents.
+fn render_blocks(blocks: &[Value]) -> String {
+    let mut out = String::new();
+
+    for block in blocks {
+        let kind = match block.get("type").and_then(|t| t.as_str()) {
+            Some(k) => k,
+            None => continue,
+        };
+        let data = match block.get(kind) {
+            Some(d) => d,
+            None => continue,
+        };
+
+        let line = match kind {
+            "paragraph" => rich_text(data),
+            "heading_1" => format!("# {}", rich_text(data)),
+            "heading_2" => format!("## {}", rich_text(data)),
+            "heading_3" => format!("### {}", rich_text(data)),
+            "bulleted_list_item" => format!("- {}", rich_text(data)),
+            "numbered_list_item" => format!("1. {}", rich_text(data)),
+            "quote" => format!("> {}", rich_text(data)),
+            "to_do" => {
+                let checked = data.get("checked").and_then(|c| c.as_bool()).unwrap_or(false);
+                let marker = if checked { "x" } else { " " };
+                format!("- [{}] {}", marker, rich_text(data))
+            }
+            "code" => {
+                let language = data
+                    .get("language")
+                    .and_then(|l| l.as_str())
+                    .unwrap_or("");
+                format!("```{}\n{}\n```", language, rich_text(data))
+            }
+            _ => continue,
+        };
+
+        out.push_str(&line);
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Concatenate a block's `rich_text` runs, honoring bold/italic/code
+/// annotations as inline Markdown.
+fn rich_text(data: &Value) -> String {
+    let runs = match data.get("rich_text").and_then(|r| r.as_array()) {
+        Some(runs) => runs,
+        None => return String::new(),
+    };
+
+    let mut out = String::new();
+    for run in runs {
+        let text = run
+            .get("plain_text")
+            .and_then(|t| t.as_str())
+            .unwrap_or("");
+        out.push_str(&annotate(text, run.get("annotations")));
+    }
+    out
+}
+
+fn annotate(text: &str, annotations: Option<&Value>) -> String {
+    let mut out = text.to_owned();
+    let annotations = match annotations {
+        Some(a) => a,
+        None => return out,
+    };
+
+    let flag = |name: &str| annotations.get(name).and_then(|v| v.as_bool()).unwrap_or(false);
+
+    if flag("code") {
+        out = format!("`{}`", out);
+    }
+    if flag("italic") {
+        out = format!("*{}*", out);
+    }
+    if flag("bold") {
+        out = format!("**{}**", out);
+    }
+    out
+}
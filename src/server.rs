@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use tokio::sync::Mutex;
+
+use crate::{run_sync, Args};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Shared handler state: the parsed args plus a lock that serializes syncs so
+/// overlapping webhook requests never race on the `dist/` tree or `state.db`.
+struct AppState {
+    args: Args,
+    secret: String,
+    sync_lock: Mutex<()>,
+}
+
+/// Start the long-running daemon exposing `POST /sync`. Blocks until the
+/// process is terminated.
+pub async fn serve(args: Args) {
+    // A network-exposed HMAC receiver with a publicly-known default secret is
+    // an auth bypass, so refuse to start without an explicit secret.
+    let secret = match args.webhook_secret.clone() {
+        Some(secret) => secret,
+        None => {
+            eprintln!("--serve requires --webhook-secret to be set");
+            std::process::exit(1);
+        }
+    };
+
+    let state = Arc::new(AppState {
+        args,
+        secret,
+        sync_lock: Mutex::new(()),
+    });
+    let app = Router::new()
+        .route("/sync", post(handle_sync))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
+        .await
+        .expect("Failed to bind webhook listener");
+
+    println!("Listening for sync webhooks on 0.0.0.0:3000");
+
+    axum::serve(listener, app)
+        .await
+        .expect("Webhook server crashed");
+}
+
+async fn handle_sync(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    if !verify_signature(&state.secret, &headers, &body) {
+        return (StatusCode::UNAUTHORIZED, Json(json!({ "status": "unauthorized" })));
+    }
+
+    // Serialize the pipeline: only one sync touches the git tree / DB at a time.
+    let _guard = state.sync_lock.lock().await;
+
+    match run_sync(&state.args).await {
+        Ok(()) => (StatusCode::OK, Json(json!({ "status": "synced" }))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "status": "error", "message": e.to_string() })),
+        ),
+    }
+}
+
+/// Recompute `HMAC-SHA256(body)` with the pre-shared key and compare it in
+/// constant time against the `X-Signature-256: sha256=<hex>` header.
+fn verify_signature(secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    let signature = match headers
+        .get("X-Signature-256")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("sha256="))
+    {
+        Some(sig) => sig,
+        None => return false,
+    };
+
+    let provided = match hex_decode(signature) {
+        Some(bytes) => bytes,
+        None => return false,
+    };
+
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+
+    // `verify_slice` performs a constant-time comparison.
+    mac.verify_slice(&provided).is_ok()
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}